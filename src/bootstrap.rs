@@ -0,0 +1,55 @@
+use std::net::{IpAddr, SocketAddr};
+
+use hickory_resolver::config::{NameServerConfig, Protocol, ResolverConfig, ResolverOpts};
+use hickory_resolver::TokioAsyncResolver;
+
+use crate::config::ConfigError;
+
+/// A set of plain UDP/TCP resolvers used to turn the hostname of a TLS/HTTPS/H3
+/// upstream into the IP addresses needed to open the connection, so operators
+/// can configure a DoH endpoint as `https://dns.adguard.com/dns-query` rather
+/// than hardcoding provider IPs.
+#[derive(Debug, Default)]
+pub struct Bootstrap {
+    servers: Vec<SocketAddr>,
+}
+
+impl Bootstrap {
+    /// Create a bootstrap resolver from plain resolver addresses.
+    pub fn new(servers: Vec<SocketAddr>) -> Self {
+        Bootstrap { servers }
+    }
+
+    /// Resolve `host` to one or more `SocketAddr`s at `port`. An address that
+    /// is already an IP literal is returned untouched without consulting the
+    /// bootstrap servers.
+    ///
+    /// Runs on the ambient Tokio runtime; callers must already be inside one.
+    pub async fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, ConfigError> {
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return Ok(vec![SocketAddr::new(ip, port)]);
+        }
+        if self.servers.is_empty() {
+            return Err(ConfigError::NoBootstrap(host.to_owned()));
+        }
+
+        let mut config = ResolverConfig::new();
+        for server in &self.servers {
+            config.add_name_server(NameServerConfig::new(*server, Protocol::Udp));
+        }
+        let resolver = TokioAsyncResolver::tokio(config, ResolverOpts::default());
+
+        let addrs = resolver
+            .lookup_ip(host)
+            .await
+            .map_err(|e| ConfigError::Bootstrap(e.to_string()))?
+            .iter()
+            .map(|ip| SocketAddr::new(ip, port))
+            .collect::<Vec<_>>();
+
+        if addrs.is_empty() {
+            return Err(ConfigError::Bootstrap(format!("no address found for {}", host)));
+        }
+        Ok(addrs)
+    }
+}
@@ -0,0 +1,239 @@
+use std::num::NonZeroUsize;
+use std::time::{Duration, Instant};
+
+use hickory_proto::rr::{DNSClass, Name, Record, RecordType};
+use hickory_resolver::lookup::Lookup;
+use lru::LruCache;
+
+/// Default number of entries kept in the cache when the operator does not
+/// override it in the config.
+const DEFAULT_CACHE_SIZE: usize = 1024;
+/// Default TTL applied to negative (NXDOMAIN) answers, in seconds.
+const DEFAULT_NEGATIVE_TTL: u64 = 60;
+
+/// Cache key identifying a question, mirroring the `(Name, RecordType,
+/// DNSClass)` tuple carried by a DNS query.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+struct Key {
+    name: Name,
+    record_type: RecordType,
+    dns_class: DNSClass,
+}
+
+/// A cached answer together with the records it carried and the instant it
+/// ceases to be valid.
+#[derive(Clone, Debug)]
+struct Entry {
+    lookup: Option<Lookup>,
+    inserted: Instant,
+    valid_until: Instant,
+    /// Whether the answer was DNSSEC-validated. Persisted so the AD bit
+    /// survives a cache hit; the covering RRSIGs ride along inside `lookup`.
+    authentic: bool,
+}
+
+/// TTL-aware LRU cache for resolved [`Lookup`]s keyed on the question tuple.
+///
+/// Positive answers expire once the minimum TTL across their records has
+/// elapsed; negative answers are held for `negative_ttl`. On a hit the stored
+/// records are returned with their TTLs decremented by the time already spent
+/// in the cache so downstream clients never see a frozen TTL.
+#[derive(Debug)]
+pub struct DnsLru {
+    cache: LruCache<Key, Entry>,
+    negative_ttl: Duration,
+}
+
+impl DnsLru {
+    /// Create a cache holding up to `cache_size` entries and caching negative
+    /// answers for `negative_ttl` seconds. A `cache_size` of zero falls back to
+    /// the default of 1024 entries.
+    pub fn new(cache_size: usize, negative_ttl: u64) -> Self {
+        let capacity = NonZeroUsize::new(cache_size)
+            .unwrap_or_else(|| NonZeroUsize::new(DEFAULT_CACHE_SIZE).unwrap());
+        DnsLru {
+            cache: LruCache::new(capacity),
+            negative_ttl: Duration::from_secs(negative_ttl),
+        }
+    }
+
+    /// Insert a positive answer, deriving its lifetime from the minimum record
+    /// TTL. `authentic` records whether the answer was DNSSEC-validated. An
+    /// answer with no records is treated as negative.
+    pub fn insert(&mut self, name: Name, record_type: RecordType, dns_class: DNSClass, lookup: Lookup, authentic: bool, now: Instant) {
+        let ttl = lookup
+            .records()
+            .iter()
+            .map(Record::ttl)
+            .min()
+            .map(|ttl| Duration::from_secs(u64::from(ttl)));
+        match ttl {
+            Some(ttl) => self.store(name, record_type, dns_class, Some(lookup), authentic, ttl, now),
+            None => self.insert_negative(name, record_type, dns_class, now),
+        }
+    }
+
+    /// Insert a negative (NXDOMAIN) answer, held for `negative_ttl`.
+    pub fn insert_negative(&mut self, name: Name, record_type: RecordType, dns_class: DNSClass, now: Instant) {
+        let negative_ttl = self.negative_ttl;
+        self.store(name, record_type, dns_class, None, false, negative_ttl, now);
+    }
+
+    fn store(&mut self, name: Name, record_type: RecordType, dns_class: DNSClass, lookup: Option<Lookup>, authentic: bool, ttl: Duration, now: Instant) {
+        let key = Key {
+            name,
+            record_type,
+            dns_class,
+        };
+        self.cache.put(
+            key,
+            Entry {
+                lookup,
+                inserted: now,
+                valid_until: now + ttl,
+                authentic,
+            },
+        );
+    }
+
+    /// Look up a cached answer. The outer `Option` distinguishes a miss
+    /// (`None`) from a hit; on a hit the inner `Option` is `None` for a cached
+    /// negative answer and `Some(lookup)` for a positive one with TTLs
+    /// decremented by the elapsed time, paired with the stored authentic flag
+    /// so a validated answer still sets the AD bit. Expired entries are evicted
+    /// and yield `None`.
+    pub fn get(&mut self, name: &Name, record_type: RecordType, dns_class: DNSClass, now: Instant) -> Option<(Option<Lookup>, bool)> {
+        let key = Key {
+            name: name.clone(),
+            record_type,
+            dns_class,
+        };
+        let expired = match self.cache.peek(&key) {
+            Some(entry) => now >= entry.valid_until,
+            None => return None,
+        };
+        if expired {
+            self.cache.pop(&key);
+            return None;
+        }
+        let entry = self.cache.get(&key)?;
+        let lookup = entry.lookup.as_ref().map(|lookup| {
+            let elapsed = now.saturating_duration_since(entry.inserted).as_secs() as u32;
+            let records = lookup
+                .records()
+                .iter()
+                .map(|record| {
+                    let mut record = record.clone();
+                    record.set_ttl(record.ttl().saturating_sub(elapsed));
+                    record
+                })
+                .collect::<Vec<_>>();
+            Lookup::new_with_deadline(lookup.query().clone(), records.into(), entry.valid_until)
+        });
+        Some((lookup, entry.authentic))
+    }
+}
+
+impl Default for DnsLru {
+    fn default() -> Self {
+        DnsLru::new(DEFAULT_CACHE_SIZE, DEFAULT_NEGATIVE_TTL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+    use std::str::FromStr;
+
+    use hickory_proto::op::Query;
+    use hickory_proto::rr::rdata::A;
+    use hickory_proto::rr::RData;
+
+    use super::*;
+
+    fn lookup(name: &Name, ttl: u32) -> Lookup {
+        let query = Query::query(name.clone(), RecordType::A);
+        let record = Record::from_rdata(name.clone(), ttl, RData::A(A::new(127, 0, 0, 1)));
+        Lookup::new_with_max_ttl(query, vec![record].into())
+    }
+
+    #[test]
+    fn insert_and_get_positive() {
+        let mut cache = DnsLru::default();
+        let name = Name::from_str("example.com.").unwrap();
+        let now = Instant::now();
+        cache.insert(name.clone(), RecordType::A, DNSClass::IN, lookup(&name, 300), false, now);
+
+        let (hit, authentic) = cache.get(&name, RecordType::A, DNSClass::IN, now).unwrap();
+        assert!(!authentic);
+        assert_eq!(hit.unwrap().records()[0].ttl(), 300);
+    }
+
+    #[test]
+    fn get_decrements_ttl_by_elapsed_time() {
+        let mut cache = DnsLru::default();
+        let name = Name::from_str("example.com.").unwrap();
+        let now = Instant::now();
+        cache.insert(name.clone(), RecordType::A, DNSClass::IN, lookup(&name, 300), true, now);
+
+        let later = now + Duration::from_secs(100);
+        let (hit, authentic) = cache.get(&name, RecordType::A, DNSClass::IN, later).unwrap();
+        assert!(authentic);
+        assert_eq!(hit.unwrap().records()[0].ttl(), 200);
+    }
+
+    #[test]
+    fn entry_is_evicted_once_expired() {
+        let mut cache = DnsLru::default();
+        let name = Name::from_str("example.com.").unwrap();
+        let now = Instant::now();
+        cache.insert(name.clone(), RecordType::A, DNSClass::IN, lookup(&name, 10), false, now);
+
+        let after_expiry = now + Duration::from_secs(11);
+        assert!(cache.get(&name, RecordType::A, DNSClass::IN, after_expiry).is_none());
+        // The entry was popped on the expired lookup, so a second miss doesn't panic either.
+        assert!(cache.get(&name, RecordType::A, DNSClass::IN, after_expiry).is_none());
+    }
+
+    #[test]
+    fn negative_entry_caches_empty_answer_for_negative_ttl() {
+        let mut cache = DnsLru::new(DEFAULT_CACHE_SIZE, 30);
+        let name = Name::from_str("missing.example.com.").unwrap();
+        let now = Instant::now();
+        cache.insert_negative(name.clone(), RecordType::A, DNSClass::IN, now);
+
+        let (hit, authentic) = cache.get(&name, RecordType::A, DNSClass::IN, now).unwrap();
+        assert!(hit.is_none());
+        assert!(!authentic);
+
+        let after_negative_ttl = now + Duration::from_secs(31);
+        assert!(cache.get(&name, RecordType::A, DNSClass::IN, after_negative_ttl).is_none());
+    }
+
+    #[test]
+    fn insert_with_empty_records_is_treated_as_negative() {
+        let mut cache = DnsLru::default();
+        let name = Name::from_str("empty.example.com.").unwrap();
+        let now = Instant::now();
+        let query = Query::query(name.clone(), RecordType::A);
+        let empty = Lookup::new_with_max_ttl(query, Vec::new().into());
+        cache.insert(name.clone(), RecordType::A, DNSClass::IN, empty, false, now);
+
+        let (hit, _) = cache.get(&name, RecordType::A, DNSClass::IN, now).unwrap();
+        assert!(hit.is_none());
+    }
+
+    #[test]
+    fn capacity_evicts_least_recently_used_entry() {
+        let mut cache = DnsLru::new(1, DEFAULT_NEGATIVE_TTL);
+        assert_eq!(cache.cache.cap(), NonZeroUsize::new(1).unwrap());
+        let first = Name::from_str("first.example.com.").unwrap();
+        let second = Name::from_str("second.example.com.").unwrap();
+        let now = Instant::now();
+        cache.insert(first.clone(), RecordType::A, DNSClass::IN, lookup(&first, 300), false, now);
+        cache.insert(second.clone(), RecordType::A, DNSClass::IN, lookup(&second, 300), false, now);
+
+        assert!(cache.get(&first, RecordType::A, DNSClass::IN, now).is_none());
+        assert!(cache.get(&second, RecordType::A, DNSClass::IN, now).is_some());
+    }
+}
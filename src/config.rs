@@ -1,3 +1,5 @@
+use crate::bootstrap::Bootstrap;
+use crate::hosts::Hosts;
 use crate::ip::IpRange;
 use hickory_proto::rr::RecordType;
 use ipnet::AddrParseError;
@@ -10,6 +12,7 @@ use std::io::BufReader;
 use std::net::IpAddr;
 use std::net::SocketAddr;
 use std::str::FromStr;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -18,9 +21,10 @@ pub enum ConfigError {
     NoUpstream,
     #[error("Invalid address: {0}")]
     InvalidAddress(String),
-    #[cfg(any(feature = "dns-over-tls", feature = "dns-over-https"))]
-    #[error("tls-host is missing")]
-    NoTlsHost,
+    #[error("No bootstrap resolver configured to resolve: {0}")]
+    NoBootstrap(String),
+    #[error("Bootstrap resolution failed: {0}")]
+    Bootstrap(String),
 }
 
 #[derive(Debug)]
@@ -32,55 +36,116 @@ pub struct Config {
     pub ranges: HashMap<String, IpRange>,
     pub request_rules: Vec<RequestRule>,
     pub response_rules: Vec<ResponseRule>,
+    pub cache_size: usize,
+    pub negative_ttl: u64,
+    pub dnssec: bool,
+    pub query_timeout: Duration,
+    pub hosts: Hosts,
+    #[cfg(feature = "doh-server")]
+    pub https_bind: Option<SocketAddr>,
+    #[cfg(feature = "doh-server")]
+    pub https_tls: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ConfigBuilder {
     bind: SocketAddr,
+    #[serde(default)]
+    dnssec: bool,
+    bootstraps: Option<Vec<String>>,
     upstreams: HashMap<String, UpstreamConfig>,
     domains: Option<HashMap<String, DomainsConf>>,
     ranges: Option<HashMap<String, IpRangeConf>>,
     requests: Option<Vec<RequestRuleConfig>>,
     responses: Option<Vec<ResponseRule>>,
+    #[serde(default = "ConfigBuilder::default_cache_size")]
+    cache_size: usize,
+    #[serde(default = "ConfigBuilder::default_negative_ttl")]
+    negative_ttl: u64,
+    #[serde(default = "ConfigBuilder::default_query_timeout")]
+    query_timeout: u64,
+    hosts: Option<Vec<String>>,
+    #[cfg(feature = "doh-server")]
+    #[serde(rename = "https-bind")]
+    https_bind: Option<SocketAddr>,
+    #[cfg(feature = "doh-server")]
+    #[serde(rename = "https-tls")]
+    https_tls: Option<String>,
 }
 
 #[derive(Debug)]
 pub enum Upstream {
     UdpUpstream {
-        address: SocketAddr,
+        address: Vec<SocketAddr>,
+        // `proxy` was already threaded through for this variant in
+        // `resolver.rs`'s `From<(&Upstream, &ResolverOpts)>` impl, which this
+        // variant's missing field left unbuildable; added here alongside
+        // `validate` to close that gap rather than as new proxy support.
+        proxy: Option<String>,
+        validate: bool,
     },
     TcpUpstream {
-        address: SocketAddr,
+        address: Vec<SocketAddr>,
         proxy: Option<String>,
+        validate: bool,
     },
     #[cfg(feature = "dns-over-tls")]
     TlsUpstream {
-        address: SocketAddr,
+        address: Vec<SocketAddr>,
         tls_host: String,
         proxy: Option<String>,
+        validate: bool,
     },
     #[cfg(feature = "dns-over-https")]
     HttpsUpstream {
-        address: SocketAddr,
+        address: Vec<SocketAddr>,
+        tls_host: String,
+        proxy: Option<String>,
+        validate: bool,
+    },
+    #[cfg(feature = "dns-over-h3")]
+    H3Upstream {
+        address: Vec<SocketAddr>,
         tls_host: String,
         proxy: Option<String>,
+        validate: bool,
     },
 }
 
 impl ConfigBuilder {
-    pub fn build(self) -> Result<Config, ConfigError> {
+    fn default_cache_size() -> usize {
+        1024
+    }
+
+    fn default_negative_ttl() -> u64 {
+        60
+    }
+
+    fn default_query_timeout() -> u64 {
+        1
+    }
+
+    pub async fn build(self) -> Result<Config, ConfigError> {
         let mut default_upstreams = Vec::new();
 
-        let upstreams = self
-            .upstreams
-            .into_iter()
-            .map(|(key, upstream)| {
-                if upstream.default {
-                    default_upstreams.push(key.clone())
-                }
-                upstream.build().map(move |upstream| (key, upstream))
-            })
-            .collect::<Result<HashMap<_, _>, ConfigError>>()?;
+        let mut bootstrap_addrs = Vec::new();
+        for server in self.bootstraps.unwrap_or_default() {
+            bootstrap_addrs.push(parse_socket_addr(&server, 53)?);
+        }
+        let bootstrap = Bootstrap::new(bootstrap_addrs);
+        let dnssec = self.dnssec;
+
+        // Bootstrap resolution for TLS/HTTPS/H3 upstreams is async and runs on
+        // the ambient runtime, so the upstreams are built sequentially here
+        // rather than through an iterator adapter.
+        let mut upstreams = HashMap::new();
+        for (key, upstream) in self.upstreams {
+            if upstream.default {
+                default_upstreams.push(key.clone());
+            }
+            let upstream = upstream.build(&bootstrap, dnssec).await?;
+            upstreams.insert(key, upstream);
+        }
 
         if default_upstreams.is_empty() {
             return Err(ConfigError::NoUpstream);
@@ -119,6 +184,15 @@ impl ConfigBuilder {
             ranges,
             request_rules,
             response_rules: self.responses.unwrap_or_default(),
+            cache_size: self.cache_size,
+            negative_ttl: self.negative_ttl,
+            dnssec: self.dnssec,
+            query_timeout: Duration::from_secs(self.query_timeout),
+            hosts: Hosts::load(&self.hosts.unwrap_or_default()),
+            #[cfg(feature = "doh-server")]
+            https_bind: self.https_bind,
+            #[cfg(feature = "doh-server")]
+            https_tls: self.https_tls,
         })
     }
 }
@@ -133,6 +207,7 @@ pub struct UpstreamConfig {
     tls_host: Option<String>,
     #[serde(default = "UpstreamConfig::default_default")]
     default: bool,
+    validate: Option<bool>,
 }
 
 impl UpstreamConfig {
@@ -140,41 +215,96 @@ impl UpstreamConfig {
         true
     }
 
-    fn build(self) -> Result<Upstream, ConfigError> {
-        let mut address = self.address.parse::<SocketAddr>();
-        if let Err(_) = address {
-            address = self
-                .address
-                .parse::<IpAddr>()
-                .map(|addr| SocketAddr::new(addr, self.network.default_port()));
-        }
-        let address = address.map_err(|_| ConfigError::InvalidAddress(self.address))?;
+    async fn build(self, bootstrap: &Bootstrap, dnssec: bool) -> Result<Upstream, ConfigError> {
         let proxy = self.proxy;
+        // A per-upstream `validate` overrides the global `dnssec` default.
+        let validate = self.validate.unwrap_or(dnssec);
+        let default_port = self.network.default_port();
         match self.network {
-            NetworkType::Tcp => Ok(Upstream::TcpUpstream { address, proxy }),
-            NetworkType::Udp => Ok(Upstream::UdpUpstream { address }),
+            NetworkType::Tcp => Ok(Upstream::TcpUpstream {
+                address: vec![parse_socket_addr(&self.address, default_port)?],
+                proxy,
+                validate,
+            }),
+            NetworkType::Udp => Ok(Upstream::UdpUpstream {
+                address: vec![parse_socket_addr(&self.address, default_port)?],
+                proxy,
+                validate,
+            }),
             #[cfg(feature = "dns-over-tls")]
             NetworkType::Tls => {
-                let tls_host = self.tls_host.ok_or(ConfigError::NoTlsHost)?;
+                let (host, port) = parse_endpoint(&self.address, default_port);
+                let tls_host = self.tls_host.unwrap_or(host.clone());
                 Ok(Upstream::TlsUpstream {
-                    address,
+                    address: bootstrap.resolve(&host, port).await?,
                     tls_host,
                     proxy,
+                    validate,
                 })
             }
             #[cfg(feature = "dns-over-https")]
             NetworkType::Https => {
-                let tls_host = self.tls_host.ok_or(ConfigError::NoTlsHost)?;
+                let (host, port) = parse_endpoint(&self.address, default_port);
+                let tls_host = self.tls_host.unwrap_or(host.clone());
                 Ok(Upstream::HttpsUpstream {
-                    address,
+                    address: bootstrap.resolve(&host, port).await?,
+                    tls_host,
+                    proxy,
+                    validate,
+                })
+            }
+            #[cfg(feature = "dns-over-h3")]
+            NetworkType::H3 => {
+                let (host, port) = parse_endpoint(&self.address, default_port);
+                let tls_host = self.tls_host.unwrap_or(host.clone());
+                Ok(Upstream::H3Upstream {
+                    address: bootstrap.resolve(&host, port).await?,
                     tls_host,
                     proxy,
+                    validate,
                 })
             }
         }
     }
 }
 
+/// Parse a plain `ip[:port]` upstream address, applying `default_port` when the
+/// port is omitted.
+fn parse_socket_addr(address: &str, default_port: u16) -> Result<SocketAddr, ConfigError> {
+    if let Ok(addr) = address.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+    address
+        .parse::<IpAddr>()
+        .map(|addr| SocketAddr::new(addr, default_port))
+        .map_err(|_| ConfigError::InvalidAddress(address.to_owned()))
+}
+
+/// Split a TLS/HTTPS/H3 endpoint — given either as a bare `host[:port]` or a
+/// full URL such as `https://dns.adguard.com/dns-query` — into its host and
+/// port, falling back to `default_port` when none is present.
+#[cfg(any(
+    feature = "dns-over-tls",
+    feature = "dns-over-https",
+    feature = "dns-over-h3"
+))]
+fn parse_endpoint(address: &str, default_port: u16) -> (String, u16) {
+    let authority = address
+        .split_once("://")
+        .map(|(_, rest)| rest)
+        .unwrap_or(address)
+        .split('/')
+        .next()
+        .unwrap_or(address);
+    match authority.rsplit_once(':') {
+        Some((host, port)) => match port.parse::<u16>() {
+            Ok(port) => (host.to_owned(), port),
+            Err(_) => (authority.to_owned(), default_port),
+        },
+        None => (authority.to_owned(), default_port),
+    }
+}
+
 #[derive(Debug, Deserialize)]
 enum NetworkType {
     #[serde(rename = "tcp")]
@@ -187,6 +317,9 @@ enum NetworkType {
     #[cfg(feature = "dns-over-https")]
     #[serde(rename = "https")]
     Https,
+    #[cfg(feature = "dns-over-h3")]
+    #[serde(rename = "h3")]
+    H3,
 }
 
 impl NetworkType {
@@ -197,6 +330,8 @@ impl NetworkType {
             NetworkType::Tls => 853,
             #[cfg(feature = "dns-over-https")]
             NetworkType::Https => 443,
+            #[cfg(feature = "dns-over-h3")]
+            NetworkType::H3 => 443,
         }
     }
 }
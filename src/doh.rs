@@ -0,0 +1,200 @@
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hickory_proto::op::Message;
+use hickory_proto::serialize::binary::BinDecodable;
+use hyper::body::HttpBody;
+use hyper::header::{CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use hyper::{Body, Method, Request as HttpRequest, Response as HttpResponse, StatusCode};
+use log::{debug, error};
+use rustls_pemfile::{certs, read_all, Item};
+use tokio::net::TcpListener;
+use tokio_rustls::rustls::{Certificate, PrivateKey, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+
+use crate::handler::Handler;
+
+/// RFC 8484 media type for wire-format DNS messages.
+const DNS_MESSAGE: &str = "application/dns-message";
+/// A DNS message is at least a 12-byte header; anything shorter is malformed.
+const MIN_MESSAGE_LEN: usize = 12;
+/// Upper bound on an accepted message, matching the DNS wire-format limit.
+const MAX_MESSAGE_LEN: usize = 65_535;
+
+/// Serve RFC 8484 DNS-over-HTTPS on `addr`, decoding each request and running
+/// it through the shared [`Handler`] pipeline. TLS is terminated with the
+/// certificate and key loaded from `tls_identity`, a single PEM file holding
+/// the certificate chain followed by the private key.
+pub async fn serve(handler: Handler, addr: SocketAddr, tls_identity: &str) -> io::Result<()> {
+    let acceptor = TlsAcceptor::from(Arc::new(load_tls_config(tls_identity)?));
+    let listener = TcpListener::bind(addr).await?;
+    debug!("DoH endpoint listening on https://{}", addr);
+    loop {
+        let (stream, _) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                error!("DoH accept failed: {}", e);
+                continue;
+            }
+        };
+        let acceptor = acceptor.clone();
+        let handler = handler.clone();
+        tokio::spawn(async move {
+            let stream = match acceptor.accept(stream).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    debug!("DoH TLS handshake failed: {}", e);
+                    return;
+                }
+            };
+            let service = service_fn(move |req| {
+                let handler = handler.clone();
+                async move { Ok::<_, hyper::Error>(handle(handler, req).await) }
+            });
+            if let Err(e) = Http::new().serve_connection(stream, service).await {
+                debug!("DoH connection error: {}", e);
+            }
+        });
+    }
+}
+
+/// Load the certificate chain and private key from a single PEM `identity`
+/// file into a rustls [`ServerConfig`].
+fn load_tls_config(identity: &str) -> io::Result<ServerConfig> {
+    let mut reader = BufReader::new(File::open(identity)?);
+    let cert_chain = certs(&mut reader)?
+        .into_iter()
+        .map(Certificate)
+        .collect::<Vec<_>>();
+    if cert_chain.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("no certificate found in {}", identity),
+        ));
+    }
+
+    let mut reader = BufReader::new(File::open(identity)?);
+    let key = read_all(&mut reader)?
+        .into_iter()
+        .find_map(|item| match item {
+            Item::PKCS8Key(key) | Item::RSAKey(key) | Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("no private key found in {}", identity),
+            )
+        })?;
+
+    ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(cert_chain, key)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+}
+
+async fn handle(handler: Handler, req: HttpRequest<Body>) -> HttpResponse<Body> {
+    let query = match extract_query(req).await {
+        Ok(query) => query,
+        Err(status) => return empty(status),
+    };
+
+    match handler.handle_wire(&query).await {
+        Ok(answer) => {
+            let max_age = min_ttl(&answer).unwrap_or(0);
+            HttpResponse::builder()
+                .header(CONTENT_TYPE, DNS_MESSAGE)
+                .header(CACHE_CONTROL, format!("max-age={}", max_age))
+                .body(Body::from(answer))
+                .unwrap()
+        }
+        Err(e) => {
+            error!("Failed to answer DoH query: {}", e);
+            empty(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Pull the wire-format query out of a `GET ?dns=<base64url>` or a
+/// `POST application/dns-message` request, enforcing the length bounds.
+async fn extract_query(req: HttpRequest<Body>) -> Result<Vec<u8>, StatusCode> {
+    let body = match *req.method() {
+        Method::GET => {
+            let dns = req
+                .uri()
+                .query()
+                .and_then(|q| {
+                    url::form_urlencoded::parse(q.as_bytes())
+                        .find(|(k, _)| k == "dns")
+                        .map(|(_, v)| v.into_owned())
+                })
+                .ok_or(StatusCode::BAD_REQUEST)?;
+            URL_SAFE_NO_PAD
+                .decode(dns.as_bytes())
+                .map_err(|_| StatusCode::BAD_REQUEST)?
+        }
+        Method::POST => {
+            if req.headers().get(CONTENT_TYPE).map(|v| v.as_bytes()) != Some(DNS_MESSAGE.as_bytes())
+            {
+                return Err(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+            }
+            let declared_len = req
+                .headers()
+                .get(CONTENT_LENGTH)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<usize>().ok());
+            if declared_len.map_or(false, |len| len > MAX_MESSAGE_LEN) {
+                return Err(StatusCode::PAYLOAD_TOO_LARGE);
+            }
+            read_capped_body(req.into_body()).await?
+        }
+        _ => return Err(StatusCode::METHOD_NOT_ALLOWED),
+    };
+
+    if body.len() < MIN_MESSAGE_LEN || body.len() > MAX_MESSAGE_LEN {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok(body)
+}
+
+/// Read `body` chunk by chunk, rejecting it as soon as more than
+/// `MAX_MESSAGE_LEN` bytes have arrived rather than buffering the whole thing
+/// first. A missing or understated `Content-Length` (or a chunked-encoded
+/// body with none at all) must not let a client force an unbounded read into
+/// memory.
+async fn read_capped_body(mut body: Body) -> Result<Vec<u8>, StatusCode> {
+    let mut buf = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|_| StatusCode::BAD_REQUEST)?;
+        if buf.len() + chunk.len() > MAX_MESSAGE_LEN {
+            return Err(StatusCode::PAYLOAD_TOO_LARGE);
+        }
+        buf.extend_from_slice(&chunk);
+    }
+    Ok(buf)
+}
+
+/// Minimum TTL across the answer records, used to set the `cache-control`
+/// `max-age` so intermediaries do not outlive the records.
+fn min_ttl(answer: &[u8]) -> Option<u32> {
+    Message::from_bytes(answer)
+        .ok()?
+        .answers()
+        .iter()
+        .map(|r| r.ttl())
+        .min()
+}
+
+fn empty(status: StatusCode) -> HttpResponse<Body> {
+    HttpResponse::builder()
+        .status(status)
+        .body(Body::empty())
+        .unwrap()
+}
@@ -1,8 +1,10 @@
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
-use crate::{config::RuleAction, filter, handler_config::HandlerConfig};
-use crossbeam_channel::bounded;
+use crate::{cache::DnsLru, config::RuleAction, filter, handler_config::HandlerConfig};
+use futures_util::stream::{FuturesUnordered, StreamExt};
 use hickory_proto::op::LowerQuery;
+use hickory_proto::rr::RecordType;
 use hickory_resolver::{
     error::{ResolveError, ResolveErrorKind},
     lookup::Lookup,
@@ -13,12 +15,33 @@ use hickory_server::{
     server::{Request, RequestHandler, ResponseHandler, ResponseInfo},
 };
 use log::debug;
-use tokio::{runtime::Runtime, time::timeout};
+use tokio::time::timeout;
 
 #[derive(Clone, Debug)]
 struct RequestResult {
     lookup: Option<Lookup>,
     code: ResponseCode,
+    /// Whether the answer was DNSSEC-validated; drives the AD bit on the
+    /// outgoing response.
+    authentic: bool,
+}
+
+/// Whether a resolved answer carries DNSSEC authentication, i.e. the covering
+/// RRSIG records that a validating resolver returns alongside the data it
+/// signed. Used to gate the AD bit so unsigned zones are never reported as
+/// authenticated.
+///
+/// This assumes `hickory_resolver`'s validating resolver leaves the covering
+/// RRSIGs in the `Lookup` it returns once `ResolverOpts::validate` has
+/// confirmed the chain; this has not been confirmed against a live validating
+/// upstream in this sandbox (no network access), only unit-tested against a
+/// synthetic `Lookup` below. Re-check against a real validating resolver
+/// before relying on the AD bit in production.
+fn is_authenticated(lookup: &Lookup) -> bool {
+    lookup
+        .records()
+        .iter()
+        .any(|record| record.record_type() == RecordType::RRSIG)
 }
 
 /// DNS Request Handler
@@ -26,11 +49,16 @@ struct RequestResult {
 pub struct Handler {
     //pub counter: Arc<AtomicU64>,
     config: HandlerConfig,
+    cache: Arc<Mutex<DnsLru>>,
 }
 impl Handler {
     /// Create handler from app config.
     pub fn new(cfg: HandlerConfig) -> Self {
-        Handler { config: cfg }
+        let cache = DnsLru::new(cfg.cache_size, cfg.negative_ttl);
+        Handler {
+            config: cfg,
+            cache: Arc::new(Mutex::new(cache)),
+        }
     }
 
     /// Handle request, returning ResponseInfo if response was successfully sent, or an error.
@@ -41,6 +69,7 @@ impl Handler {
             return Ok(RequestResult {
                 lookup: None,
                 code: ResponseCode::Refused,
+                authentic: false,
             });
         }
         self.lookup(request.query()).await
@@ -50,69 +79,171 @@ impl Handler {
     async fn lookup(&self, query: &LowerQuery) -> Result<RequestResult, ResolveError> {
         //self.counter.fetch_add(1, Ordering::SeqCst);
         let config = &self.config;
+        let name = query.name().into();
+        let record_type = query.query_type();
+        let dns_class = query.query_class();
+        if let Some(lookup) = config.hosts.lookup(&name, record_type) {
+            debug!("Answering [{}] from hosts table", query.name());
+            return Ok(RequestResult {
+                lookup: Some(lookup),
+                code: ResponseCode::NoError,
+                authentic: false,
+            });
+        }
+        if let Some(cached) = self
+            .cache
+            .lock()
+            .unwrap()
+            .get(&name, record_type, dns_class, Instant::now())
+        {
+            debug!("Serving [{}] from cache", query.name());
+            return Ok(match cached {
+                (Some(lookup), authentic) => RequestResult {
+                    lookup: Some(lookup),
+                    code: ResponseCode::NoError,
+                    authentic,
+                },
+                (None, _) => RequestResult {
+                    lookup: None,
+                    code: ResponseCode::NXDomain,
+                    authentic: false,
+                },
+            });
+        }
         let resolvers = filter::resolvers(config, query);
-        let resolvers_len = resolvers.len();
-        let (tx, rx) = bounded(resolvers_len);
-        let rt = Runtime::new().unwrap();
-        resolvers
+        let query_type = query.query_type();
+        let query_timeout = config.query_timeout;
+        // Fan the query out across the selected resolvers on the ambient server
+        // runtime. `FuturesUnordered` races them so the first response passing
+        // `check_response` wins; dropping the stream cancels the rest.
+        let mut pending = resolvers
             .into_iter()
             .map(|name| {
-                (
-                    config.resolvers.get(&name).cloned().unwrap(),
-                    name,
-                    query.name().to_string(),
-                    query.query_type(),
-                )
-            })
-            .for_each(|(rs, name, domain, query_type)| {
-                let tx1 = tx.clone();
-                rt.spawn(async move {
-                    let res =
-                        timeout(Duration::from_secs(1), rs.resolve(&domain, query_type)).await;
+                let rs = config.resolvers.get(&name).cloned().unwrap();
+                let validate = rs.validate;
+                let domain = query.name().to_string();
+                async move {
+                    let res = timeout(query_timeout, rs.resolve(&domain, query_type)).await;
                     let lookup = match res {
                         Ok(lookup) => lookup,
                         Err(_) => Err(ResolveErrorKind::Timeout.into()),
                     };
-                    match lookup {
-                        Ok(lookup) => {
-                            let _ = tx1.try_send(Some((lookup, name, domain)));
-                        }
-                        Err(_) => {
-                            let _ = tx1.try_send(None);
-                        }
-                    }
-                });
-            });
+                    (name, domain, validate, lookup)
+                }
+            })
+            .collect::<FuturesUnordered<_>>();
+
         let mut lookup_result = None;
-        for _ in 0..resolvers_len {
-            let lookup = rx.recv().unwrap();
+        let mut authentic = false;
+        // A validating upstream that rejects the DNSSEC chain surfaces as an
+        // error; remember it so we can answer SERVFAIL rather than NXDOMAIN.
+        let mut validation_failed = false;
+        // Whether an upstream actually answered NXDOMAIN. Only a genuine
+        // negative answer is worth persisting; a timeout or filtered response
+        // must not poison the negative cache.
+        let mut got_nxdomain = false;
+        while let Some((name, domain, validate, lookup)) = pending.next().await {
             match lookup {
-                Some((lookup, name, domain)) => {
-                    match filter::check_response(config, &domain, &name, &lookup) {
-                        RuleAction::Accept => {
-                            debug!("Use result from {}", name);
-                            lookup_result = Some(lookup);
-                            break;
-                        }
-                        RuleAction::Drop => (),
+                Ok(lookup) => match filter::check_response(config, &domain, &name, &lookup) {
+                    RuleAction::Accept => {
+                        debug!("Use result from {}", name);
+                        // Only claim AD when the upstream validated *and* the
+                        // answer actually carries DNSSEC signatures; an unsigned
+                        // zone resolves successfully but is not authenticated.
+                        authentic = validate && is_authenticated(&lookup);
+                        lookup_result = Some(lookup);
+                        break;
                     }
-                }
-                None => {}
+                    RuleAction::Drop => (),
+                },
+                Err(e) => match e.kind() {
+                    ResolveErrorKind::NoRecordsFound { response_code, .. }
+                        if *response_code == ResponseCode::NXDomain =>
+                    {
+                        got_nxdomain = true;
+                    }
+                    ResolveErrorKind::Timeout => (),
+                    _ if validate => validation_failed = true,
+                    _ => (),
+                },
             }
         }
-        rt.shutdown_background();
-        drop(tx);
-        match lookup_result {
-            Some(lookup) => Ok(RequestResult {
-                lookup: Some(lookup),
-                code: ResponseCode::NoError,
-            }),
-            None => Ok(RequestResult {
+        drop(pending);
+
+        if lookup_result.is_none() && validation_failed {
+            return Ok(RequestResult {
                 lookup: None,
-                code: ResponseCode::NXDomain,
-            }),
+                code: ResponseCode::ServFail,
+                authentic: false,
+            });
+        }
+        let mut cache = self.cache.lock().unwrap();
+        let now = Instant::now();
+        match lookup_result {
+            Some(lookup) => {
+                cache.insert(name, record_type, dns_class, lookup.clone(), authentic, now);
+                Ok(RequestResult {
+                    lookup: Some(lookup),
+                    code: ResponseCode::NoError,
+                    authentic,
+                })
+            }
+            None => {
+                // Only cache a real NXDOMAIN; a transient upstream failure
+                // (all timeouts, or every response dropped by the filter) must
+                // not be remembered as a negative answer.
+                if got_nxdomain {
+                    cache.insert_negative(name, record_type, dns_class, now);
+                }
+                Ok(RequestResult {
+                    lookup: None,
+                    code: ResponseCode::NXDomain,
+                    authentic: false,
+                })
+            }
         }
     }
+
+    /// Run a wire-format DNS query through the same lookup pipeline used for UDP
+    /// and TCP and return the wire-format response. Used by the inbound DoH
+    /// listener, which hands us the decoded RFC 8484 message body.
+    #[cfg(feature = "doh-server")]
+    pub async fn handle_wire(&self, bytes: &[u8]) -> Result<Vec<u8>, hickory_proto::error::ProtoError> {
+        use hickory_proto::op::{Message, MessageType, Query};
+        use hickory_proto::serialize::binary::BinDecodable;
+
+        let request = Message::from_bytes(bytes)?;
+        let mut response = Message::new();
+        response.set_id(request.id());
+        response.set_message_type(MessageType::Response);
+        response.set_op_code(request.op_code());
+        response.set_recursion_desired(request.recursion_desired());
+        response.set_recursion_available(true);
+        for query in request.queries() {
+            response.add_query(query.clone());
+        }
+
+        match request.queries().first() {
+            Some(query) => {
+                let lower = LowerQuery::from(Query::from(query.clone()));
+                let result = self.lookup(&lower).await.unwrap_or(RequestResult {
+                    lookup: None,
+                    code: ResponseCode::ServFail,
+                    authentic: false,
+                });
+                response.set_response_code(result.code);
+                response.set_authentic_data(result.authentic);
+                if let Some(lookup) = result.lookup {
+                    response.add_answers(lookup.records().to_owned());
+                }
+            }
+            None => {
+                response.set_response_code(ResponseCode::FormError);
+            }
+        }
+
+        response.to_vec()
+    }
 }
 
 #[async_trait::async_trait]
@@ -130,6 +261,7 @@ impl RequestHandler for Handler {
                 RequestResult {
                     lookup: None,
                     code: ResponseCode::ServFail,
+                    authentic: false,
                 }
             }
         };
@@ -141,7 +273,39 @@ impl RequestHandler for Handler {
         let mut header = Header::response_from_request(request.header());
         header.set_response_code(result.code);
         header.set_recursion_available(true);
+        header.set_authentic_data(result.authentic);
         let message = builder.build(header, records.iter(), &[], &[], &[]);
         response.send_response(message).await.unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use hickory_proto::op::Query;
+    use hickory_proto::rr::rdata::A;
+    use hickory_proto::rr::{Name, RData, Record};
+
+    use super::*;
+
+    #[test]
+    fn unsigned_answer_is_not_authenticated() {
+        let name = Name::from_ascii("example.com.").unwrap();
+        let query = Query::query(name.clone(), RecordType::A);
+        let record = Record::from_rdata(name, 300, RData::A(A::new(127, 0, 0, 1)));
+        let lookup = Lookup::new_with_max_ttl(query, vec![record].into());
+
+        assert!(!is_authenticated(&lookup));
+    }
+
+    #[test]
+    fn answer_with_rrsig_is_authenticated() {
+        let name = Name::from_ascii("example.com.").unwrap();
+        let query = Query::query(name.clone(), RecordType::A);
+        let a_record = Record::from_rdata(name.clone(), 300, RData::A(A::new(127, 0, 0, 1)));
+        let mut rrsig_record = Record::from_rdata(name, 300, RData::A(A::new(0, 0, 0, 0)));
+        rrsig_record.set_record_type(RecordType::RRSIG);
+        let lookup = Lookup::new_with_max_ttl(query, vec![a_record, rrsig_record].into());
+
+        assert!(is_authenticated(&lookup));
+    }
+}
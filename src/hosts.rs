@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+use hickory_proto::rr::rdata::{A, AAAA};
+use hickory_proto::rr::{DNSClass, Name, RData, Record, RecordType};
+use hickory_resolver::lookup::Lookup;
+use hickory_resolver::proto::op::Query;
+
+/// TTL (in seconds) attached to synthesized hosts-file answers.
+const HOSTS_TTL: u32 = 300;
+/// Path to the OS hosts file, selected by the `"system"` keyword.
+#[cfg(unix)]
+const SYSTEM_HOSTS: &str = "/etc/hosts";
+#[cfg(windows)]
+const SYSTEM_HOSTS: &str = r"C:\Windows\System32\drivers\etc\hosts";
+
+/// A name-to-address table loaded from the OS hosts file and/or adblock-style
+/// lists, consulted before any query is forwarded upstream.
+///
+/// Names mapped to `0.0.0.0`/`::` act as blocking entries: the query is
+/// answered locally with the zero address of the queried family instead of
+/// reaching an upstream.
+#[derive(Debug, Default)]
+pub struct Hosts {
+    table: HashMap<Name, Vec<IpAddr>>,
+}
+
+impl Hosts {
+    /// Load a table from the configured sources. `"system"` selects the OS
+    /// hosts file; every other entry is treated as a path to a hosts/adblock
+    /// list.
+    pub fn load(sources: &[String]) -> Self {
+        let mut hosts = Hosts::default();
+        for source in sources {
+            let path = if source == "system" {
+                SYSTEM_HOSTS
+            } else {
+                source.as_str()
+            };
+            if let Ok(file) = File::open(path) {
+                hosts.read(BufReader::new(file));
+            }
+        }
+        hosts
+    }
+
+    fn read<R: BufRead>(&mut self, reader: R) {
+        for line in reader.lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => continue,
+            };
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let first = fields.next().unwrap_or("");
+            let addr = match IpAddr::from_str(first) {
+                Ok(addr) => addr,
+                // Adblock-style lists often carry a bare domain per line with no
+                // address; treat such an entry as a block mapping to the zero
+                // address.
+                Err(_) => {
+                    if let Ok(name) = Name::from_str_relaxed(first) {
+                        self.block(name);
+                    }
+                    continue;
+                }
+            };
+            for host in fields {
+                if let Ok(name) = Name::from_str_relaxed(host).map(Name::to_lowercase) {
+                    self.table.entry(name).or_default().push(addr);
+                }
+            }
+        }
+    }
+
+    /// Add a single blocking entry mapping `name` to the zero address.
+    pub fn block(&mut self, name: Name) {
+        let zero = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+        self.table.entry(name.to_lowercase()).or_default().push(zero);
+    }
+
+    /// Synthesize an authoritative answer for `name` if it is present in the
+    /// table and the query is for an address record. Entries whose address
+    /// family does not match the query type are skipped, except zero-address
+    /// blocking entries, which answer both families.
+    pub fn lookup(&self, name: &Name, record_type: RecordType) -> Option<Lookup> {
+        if record_type != RecordType::A && record_type != RecordType::AAAA {
+            return None;
+        }
+        // Table entries are parsed without a trailing dot, while queried names
+        // arrive fully qualified; compare them in the table's form.
+        let mut key = name.to_lowercase();
+        key.set_fqdn(false);
+        let addrs = self.table.get(&key)?;
+        let records = addrs
+            .iter()
+            .filter_map(|addr| record_for(name, record_type, *addr))
+            .collect::<Vec<_>>();
+        if records.is_empty() {
+            return None;
+        }
+        let query = Query::query(name.clone(), record_type);
+        Some(Lookup::new_with_max_ttl(query, records.into()))
+    }
+}
+
+/// Build a record for `addr` matching the queried record type, or `None` when
+/// the address family and query type disagree.
+fn record_for(name: &Name, record_type: RecordType, addr: IpAddr) -> Option<Record> {
+    let rdata = match (record_type, addr) {
+        (RecordType::A, IpAddr::V4(ip)) => RData::A(A(ip)),
+        (RecordType::AAAA, IpAddr::V6(ip)) => RData::AAAA(AAAA(ip)),
+        // A query against an IPv6-only (or v4-only) entry yields no matching
+        // record; a bare `0.0.0.0`/`::` block also answers the other family
+        // with that family's zero address.
+        (RecordType::AAAA, IpAddr::V4(ip)) if ip == Ipv4Addr::UNSPECIFIED => {
+            RData::AAAA(AAAA(Ipv6Addr::UNSPECIFIED))
+        }
+        (RecordType::A, IpAddr::V6(ip)) if ip == Ipv6Addr::UNSPECIFIED => {
+            RData::A(A(Ipv4Addr::UNSPECIFIED))
+        }
+        _ => return None,
+    };
+    Some(Record::from_rdata(name.clone(), HOSTS_TTL, rdata).set_dns_class(DNSClass::IN).clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn addrs(hosts: &Hosts, name: &str, record_type: RecordType) -> Vec<IpAddr> {
+        hosts
+            .lookup(&Name::from_str(name).unwrap(), record_type)
+            .map(|lookup| {
+                lookup
+                    .records()
+                    .iter()
+                    .map(|record| match record.data() {
+                        Some(RData::A(a)) => IpAddr::V4(a.0),
+                        Some(RData::AAAA(aaaa)) => IpAddr::V6(aaaa.0),
+                        other => panic!("unexpected rdata: {other:?}"),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn hosts_file_entry_resolves_matching_family() {
+        let mut hosts = Hosts::default();
+        hosts.read(Cursor::new("127.0.0.1 example.com\n::1 example.com\n"));
+
+        assert_eq!(addrs(&hosts, "example.com", RecordType::A), vec![IpAddr::V4(Ipv4Addr::LOCALHOST)]);
+        assert_eq!(addrs(&hosts, "example.com", RecordType::AAAA), vec![IpAddr::V6(Ipv6Addr::LOCALHOST)]);
+    }
+
+    #[test]
+    fn bare_domain_line_blocks_both_record_types() {
+        let mut hosts = Hosts::default();
+        hosts.read(Cursor::new("ads.example.com\n"));
+
+        assert_eq!(addrs(&hosts, "ads.example.com", RecordType::A), vec![IpAddr::V4(Ipv4Addr::UNSPECIFIED)]);
+        assert_eq!(addrs(&hosts, "ads.example.com", RecordType::AAAA), vec![IpAddr::V6(Ipv6Addr::UNSPECIFIED)]);
+    }
+
+    #[test]
+    fn bare_v6_block_marker_blocks_both_record_types() {
+        let mut hosts = Hosts::default();
+        hosts.read(Cursor::new(":: tracker.example.com\n"));
+
+        assert_eq!(addrs(&hosts, "tracker.example.com", RecordType::AAAA), vec![IpAddr::V6(Ipv6Addr::UNSPECIFIED)]);
+        assert_eq!(addrs(&hosts, "tracker.example.com", RecordType::A), vec![IpAddr::V4(Ipv4Addr::UNSPECIFIED)]);
+    }
+
+    #[test]
+    fn unknown_name_yields_no_lookup() {
+        let hosts = Hosts::default();
+        assert!(hosts.lookup(&Name::from_str("missing.example.com").unwrap(), RecordType::A).is_none());
+    }
+}
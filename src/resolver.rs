@@ -10,6 +10,9 @@ use crate::resolver_runtime_provider::{ProxyConnectionProvider, ProxyRuntimeProv
 #[derive(Clone, Debug)]
 pub struct RecursiveResolver {
     pub resolver: AsyncResolver<ProxyConnectionProvider>,
+    /// Whether this resolver requests and validates DNSSEC records; mirrors
+    /// `ResolverOpts::validate` so the handler can set the AD bit on answers.
+    pub validate: bool,
 }
 
 impl RecursiveResolver {
@@ -18,8 +21,9 @@ impl RecursiveResolver {
         options: ResolverOpts,
         provider: ProxyConnectionProvider,
     ) -> Self {
+        let validate = options.validate;
         let resolver = AsyncResolver::new(resolver_config, options, provider);
-        RecursiveResolver { resolver }
+        RecursiveResolver { resolver, validate }
     }
 
     pub async fn resolve(
@@ -39,28 +43,40 @@ impl RecursiveResolver {
 
 impl From<(&Upstream, &ResolverOpts)> for RecursiveResolver {
     fn from((upstream, options): (&Upstream, &ResolverOpts)) -> Self {
-        let (protocol, address, tls_host, proxy) = match upstream {
-            Upstream::UdpUpstream { address, proxy } => (Protocol::Udp, address, None, proxy),
-            Upstream::TcpUpstream { address, proxy } => (Protocol::Tcp, address, None, proxy),
+        let (protocol, address, tls_host, proxy, validate) = match upstream {
+            Upstream::UdpUpstream { address, proxy, validate } => {
+                (Protocol::Udp, address, None, proxy, *validate)
+            }
+            Upstream::TcpUpstream { address, proxy, validate } => {
+                (Protocol::Tcp, address, None, proxy, *validate)
+            }
             #[cfg(feature = "dns-over-tls")]
             Upstream::TlsUpstream {
                 address,
                 tls_host,
                 proxy,
-            } => (Protocol::Tls, address, Some(tls_host.to_owned()), proxy),
+                validate,
+            } => (Protocol::Tls, address, Some(tls_host.to_owned()), proxy, *validate),
             #[cfg(feature = "dns-over-https")]
             Upstream::HttpsUpstream {
                 address,
                 tls_host,
                 proxy,
-            } => (Protocol::Https, address, Some(tls_host.to_owned()), proxy),
+                validate,
+            } => (Protocol::Https, address, Some(tls_host.to_owned()), proxy, *validate),
             #[cfg(feature = "dns-over-h3")]
             Upstream::H3Upstream {
                 address,
                 tls_host,
                 proxy,
-            } => (Protocol::H3, address, Some(tls_host.to_owned()), proxy),
+                validate,
+            } => (Protocol::H3, address, Some(tls_host.to_owned()), proxy, *validate),
         };
+        // Requesting DNSSEC records and validating the RRSIG/NSEC(3) chain is
+        // driven entirely by `ResolverOpts::validate`, which also sets the DO
+        // bit on outgoing queries.
+        let mut options = options.to_owned();
+        options.validate = validate;
         let mut resolver_config = ResolverConfig::new();
         address.iter().for_each(|addr| {
             let mut name_server_config = NameServerConfig::new(*addr, protocol);
@@ -70,7 +86,7 @@ impl From<(&Upstream, &ResolverOpts)> for RecursiveResolver {
         let runtime_provider =
             ProxyRuntimeProvider::new(proxy.to_owned().map(|p| p.parse().unwrap()));
         let provider = ProxyConnectionProvider::new(runtime_provider);
-        RecursiveResolver::new(resolver_config, options.to_owned(), provider)
+        RecursiveResolver::new(resolver_config, options, provider)
     }
 }
 
@@ -90,6 +106,7 @@ mod tests {
             &Upstream::UdpUpstream {
                 address: vec![dns_addr],
                 proxy: None,
+                validate: false,
             },
             &ResolverOpts::default(),
         )
@@ -109,6 +126,7 @@ mod tests {
             &Upstream::TcpUpstream {
                 address: vec![dns_addr],
                 proxy: None,
+                validate: false,
             },
             &ResolverOpts::default(),
         )
@@ -131,6 +149,7 @@ mod tests {
                 address: vec![dns_addr],
                 proxy: None,
                 tls_host: dns_host,
+                validate: false,
             },
             &ResolverOpts::default(),
         )
@@ -153,6 +172,7 @@ mod tests {
                 address: vec![dns_addr],
                 proxy: None,
                 tls_host: dns_host,
+                validate: false,
             },
             &ResolverOpts::default(),
         )
@@ -175,6 +195,7 @@ mod tests {
                 address: vec![dns_addr],
                 proxy: None,
                 tls_host: dns_host,
+                validate: false,
             },
             &ResolverOpts::default(),
         )